@@ -0,0 +1,222 @@
+//! A high-level client that drives a DE1 over its serial tether, handling
+//! subscription bookkeeping and frame decoding so callers can work in terms
+//! of typed `Packet`s instead of raw wire frames.
+//!
+//! `Client<W>` only holds the frame-decoding state; `SyncClient`/`AsyncClient`
+//! add the request methods, each implemented once for a `Client` wrapping a
+//! [`SyncWriter`] or [`AsyncWriter`] respectively. The wrapper picks which
+//! trait the client gets, so a `W` that happens to implement both
+//! `embedded_io::Write` and `embedded_io_async::Write` can't make
+//! `subscribe`/`unsubscribe`/etc. ambiguous.
+
+use crate::{
+    profile::Profile,
+    serial::{Frame, LineReader},
+    Command, Packet, RequestedState, Result, ShotSettings, State,
+};
+
+/// Marks a writer as blocking-only, so a `Client<SyncWriter<W>>` only gets
+/// `SyncClient`, even if `W` also implements `embedded_io_async::Write`.
+pub struct SyncWriter<W>(pub W);
+
+impl<W: embedded_io::ErrorType> embedded_io::ErrorType for SyncWriter<W> {
+    type Error = W::Error;
+}
+
+impl<W: embedded_io::Write> embedded_io::Write for SyncWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> core::result::Result<(), Self::Error> {
+        self.0.flush()
+    }
+}
+
+/// Marks a writer as async-only, so a `Client<AsyncWriter<W>>` only gets
+/// `AsyncClient`, even if `W` also implements `embedded_io::Write`.
+pub struct AsyncWriter<W>(pub W);
+
+impl<W: embedded_io_async::ErrorType> embedded_io_async::ErrorType for AsyncWriter<W> {
+    type Error = W::Error;
+}
+
+impl<W: embedded_io_async::Write> embedded_io_async::Write for AsyncWriter<W> {
+    async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        self.0.write(buf).await
+    }
+}
+
+/// Drives a DE1 connected over `W`, sending subscribe/unsubscribe requests
+/// and decoding incoming frames into typed `Packet`s. It does not record
+/// which subscriptions are active.
+pub struct Client<W> {
+    writer: W,
+    line_reader: LineReader<64>,
+}
+
+impl<W> Client<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            line_reader: LineReader::new(),
+        }
+    }
+
+    /// Feeds one byte received from the transport into the internal line
+    /// reader, returning a decoded `Packet` once `c` completes a frame.
+    pub fn poll(&mut self, c: char) -> Result<Option<Packet>> {
+        let Some(frame) = self.line_reader.handle_char(c)? else {
+            return Ok(None);
+        };
+
+        match frame {
+            Frame::FromDe1(command) | Frame::ToDe1(command) => {
+                Ok(Some(Packet::from_command(&command)?))
+            }
+            Frame::Subscribe(c) => Ok(Some(Packet::Subscribe(c))),
+            Frame::Unsubscribe(c) => Ok(Some(Packet::Unsubscribe(c))),
+        }
+    }
+}
+
+/// Request methods for a `Client` driven by a blocking transport.
+pub trait SyncClient {
+    /// Subscribes to notifications for `command` (`<+X>`).
+    fn subscribe(&mut self, command: Command) -> Result<()>;
+
+    /// Unsubscribes from notifications for `command` (`<-X>`).
+    fn unsubscribe(&mut self, command: Command) -> Result<()>;
+
+    /// Requests the machine transition to `state`.
+    fn set_requested_state(&mut self, state: State) -> Result<()>;
+
+    /// Writes the steam/hot-water/espresso shot settings.
+    fn write_shot_settings(&mut self, settings: ShotSettings) -> Result<()>;
+
+    /// Uploads a shot profile: one header followed by its frames, in order.
+    fn upload_profile(&mut self, profile: &Profile) -> Result<()>;
+}
+
+impl<W: embedded_io::Write> SyncClient for Client<SyncWriter<W>> {
+    fn subscribe(&mut self, command: Command) -> Result<()> {
+        Frame::Subscribe(command.serial_command()).write_blocking(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn unsubscribe(&mut self, command: Command) -> Result<()> {
+        Frame::Unsubscribe(command.serial_command()).write_blocking(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn set_requested_state(&mut self, state: State) -> Result<()> {
+        Packet::RequestedState(RequestedState { state })
+            .to_frame()?
+            .write_blocking(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn write_shot_settings(&mut self, settings: ShotSettings) -> Result<()> {
+        Packet::ShotSettings(settings)
+            .to_frame()?
+            .write_blocking(&mut self.writer)?;
+        Ok(())
+    }
+
+    fn upload_profile(&mut self, profile: &Profile) -> Result<()> {
+        for packet in profile.to_packets() {
+            packet.to_frame()?.write_blocking(&mut self.writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Request methods for a `Client` driven by an async transport.
+pub trait AsyncClient {
+    /// Subscribes to notifications for `command` (`<+X>`).
+    async fn subscribe(&mut self, command: Command) -> Result<()>;
+
+    /// Unsubscribes from notifications for `command` (`<-X>`).
+    async fn unsubscribe(&mut self, command: Command) -> Result<()>;
+
+    /// Requests the machine transition to `state`.
+    async fn set_requested_state(&mut self, state: State) -> Result<()>;
+
+    /// Writes the steam/hot-water/espresso shot settings.
+    async fn write_shot_settings(&mut self, settings: ShotSettings) -> Result<()>;
+
+    /// Uploads a shot profile: one header followed by its frames, in order.
+    async fn upload_profile(&mut self, profile: &Profile) -> Result<()>;
+}
+
+impl<W: embedded_io_async::Write> AsyncClient for Client<AsyncWriter<W>> {
+    async fn subscribe(&mut self, command: Command) -> Result<()> {
+        Frame::Subscribe(command.serial_command())
+            .write(&mut self.writer)
+            .await?;
+        Ok(())
+    }
+
+    async fn unsubscribe(&mut self, command: Command) -> Result<()> {
+        Frame::Unsubscribe(command.serial_command())
+            .write(&mut self.writer)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_requested_state(&mut self, state: State) -> Result<()> {
+        Packet::RequestedState(RequestedState { state })
+            .to_frame()?
+            .write(&mut self.writer)
+            .await?;
+        Ok(())
+    }
+
+    async fn write_shot_settings(&mut self, settings: ShotSettings) -> Result<()> {
+        Packet::ShotSettings(settings)
+            .to_frame()?
+            .write(&mut self.writer)
+            .await?;
+        Ok(())
+    }
+
+    async fn upload_profile(&mut self, profile: &Profile) -> Result<()> {
+        for packet in profile.to_packets() {
+            packet.to_frame()?.write(&mut self.writer).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    #[test]
+    fn sync_client_subscribe_writes_subscribe_frame() {
+        let mut client = Client::new(SyncWriter(std::vec::Vec::new()));
+        client.subscribe(Command::ShotSample).unwrap();
+        assert_eq!(client.writer.0, b"<+M>\n");
+    }
+
+    #[futures_test::test]
+    async fn async_client_subscribe_writes_subscribe_frame() {
+        let mut client = Client::new(AsyncWriter(std::vec::Vec::new()));
+        client.subscribe(Command::ShotSample).await.unwrap();
+        assert_eq!(client.writer.0, b"<+M>\n");
+    }
+
+    #[test]
+    fn poll_decodes_a_completed_frame() {
+        let mut client = Client::new(std::vec::Vec::<u8>::new());
+        for c in "<+M>\n".chars() {
+            if let Some(packet) = client.poll(c).unwrap() {
+                assert_eq!(packet, Packet::Subscribe('M'));
+                return;
+            }
+        }
+        panic!("poll never completed a frame");
+    }
+}
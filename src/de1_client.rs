@@ -0,0 +1,211 @@
+//! Host-side client for the DE1: issues commands over a `Transport` and
+//! correlates the machine's replies with whatever is waiting on them, the
+//! way request/response libraries that share one stream (e.g. netapp,
+//! x11rb) track outstanding requests by id.
+
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex, signal::Signal};
+
+use crate::{
+    serial::{Frame, LineReader},
+    transport::{Transport, TransportWriter},
+    Command, Error, MmrOpperation, Packet, RequestedState, Result, ShotSample, State, StateInfo,
+    WaterLevels,
+};
+
+/// Largest number of concurrently outstanding `read_mmr` calls.
+const MAX_IN_FLIGHT_MMR_READS: usize = 4;
+
+/// Drives a DE1 from the controller's side of the wire: sends commands and
+/// routes each decoded reply to whichever `read_mmr` call (or subscription
+/// stream) is waiting for it.
+pub struct De1Client<T: Transport> {
+    transport: Mutex<NoopRawMutex, T>,
+    line_reader: Mutex<NoopRawMutex, LineReader<64>>,
+    // `mmr_slots[i]` holds the address `read_mmr` is waiting on for
+    // `mmr_signals[i]`, or `None` if the slot is free.
+    mmr_slots: Mutex<NoopRawMutex, [Option<u32>; MAX_IN_FLIGHT_MMR_READS]>,
+    mmr_signals: [Signal<NoopRawMutex, MmrOpperation>; MAX_IN_FLIGHT_MMR_READS],
+    shot_sample: Signal<NoopRawMutex, ShotSample>,
+    state_info: Signal<NoopRawMutex, StateInfo>,
+    water_levels: Signal<NoopRawMutex, WaterLevels>,
+}
+
+impl<T: Transport> De1Client<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport: Mutex::new(transport),
+            line_reader: Mutex::new(LineReader::new()),
+            mmr_slots: Mutex::new([None; MAX_IN_FLIGHT_MMR_READS]),
+            mmr_signals: core::array::from_fn(|_| Signal::new()),
+            shot_sample: Signal::new(),
+            state_info: Signal::new(),
+            water_levels: Signal::new(),
+        }
+    }
+
+    /// Subscribes to notifications for `command` (`<+X>`).
+    pub async fn subscribe(&self, command: Command) -> Result<()> {
+        self.send_frame(&Frame::Subscribe(command.serial_command()))
+            .await
+    }
+
+    /// Unsubscribes from notifications for `command` (`<-X>`).
+    pub async fn unsubscribe(&self, command: Command) -> Result<()> {
+        self.send_frame(&Frame::Unsubscribe(command.serial_command()))
+            .await
+    }
+
+    /// Requests the machine transition to `state`.
+    pub async fn set_requested_state(&self, state: State) -> Result<()> {
+        self.send(&Packet::RequestedState(RequestedState { state }))
+            .await
+    }
+
+    /// Reads `len + 1` words (per the `(len + 1) * 4` byte convention) starting
+    /// at `addr`, awaiting the machine's matching `ReadFromMmr` response.
+    ///
+    /// Fails with `Error::Unknown` if `addr` already has a `read_mmr` call in
+    /// flight: `handle_mmr_response` routes a reply to the first slot
+    /// matching its address, so two concurrent reads of the same address
+    /// would otherwise race for the one response and leave the other
+    /// waiting forever.
+    pub async fn read_mmr(&self, addr: u32, len: u8) -> Result<MmrOpperation> {
+        let index = {
+            let mut slots = self.mmr_slots.lock().await;
+            if slots.contains(&Some(addr)) {
+                return Err(Error::Unknown);
+            }
+            let index = slots.iter().position(|s| s.is_none()).ok_or(Error::Unknown)?;
+            slots[index] = Some(addr);
+            index
+        };
+
+        if let Err(e) = self
+            .send(&Packet::ReadFromMmr(MmrOpperation {
+                len,
+                addr,
+                data: [0u8; 16],
+            }))
+            .await
+        {
+            self.mmr_slots.lock().await[index] = None;
+            return Err(e);
+        }
+
+        let result = self.mmr_signals[index].wait().await;
+
+        self.mmr_slots.lock().await[index] = None;
+
+        Ok(result)
+    }
+
+    /// Awaits the next `ShotSample` notification.
+    pub async fn shot_sample(&self) -> ShotSample {
+        self.shot_sample.wait().await
+    }
+
+    /// Awaits the next `StateInfo` notification.
+    pub async fn state_info(&self) -> StateInfo {
+        self.state_info.wait().await
+    }
+
+    /// Awaits the next `WaterLevels` notification.
+    pub async fn water_levels(&self) -> WaterLevels {
+        self.water_levels.wait().await
+    }
+
+    /// Reads from the transport and decodes/routes frames forever. Spawn
+    /// this alongside calls to the methods above.
+    pub async fn run(&self) -> ! {
+        let mut buf = [0u8; 64];
+        loop {
+            let read_len = {
+                let mut transport = self.transport.lock().await;
+                transport.read(&mut buf).await
+            };
+
+            for c in buf[..read_len].iter().map(|b| *b as char) {
+                let frame = {
+                    let mut line_reader = self.line_reader.lock().await;
+                    line_reader.handle_char(c)
+                };
+                if let Ok(Some(frame)) = frame {
+                    self.handle_frame(frame).await;
+                }
+            }
+        }
+    }
+
+    async fn handle_frame(&self, frame: Frame) {
+        let command = match frame {
+            Frame::FromDe1(command) | Frame::ToDe1(command) => command,
+            Frame::Subscribe(_) | Frame::Unsubscribe(_) => return,
+        };
+
+        let Ok(packet) = Packet::from_command(&command) else {
+            return;
+        };
+
+        match packet {
+            Packet::ReadFromMmr(op) => self.handle_mmr_response(op).await,
+            Packet::ShotSample(sample) => self.shot_sample.signal(sample),
+            Packet::StateInfo(info) => self.state_info.signal(info),
+            Packet::WaterLevels(levels) => self.water_levels.signal(levels),
+            _ => (),
+        }
+    }
+
+    async fn handle_mmr_response(&self, op: MmrOpperation) {
+        let slots = self.mmr_slots.lock().await;
+        if let Some(index) = slots.iter().position(|s| *s == Some(op.addr)) {
+            self.mmr_signals[index].signal(op);
+        }
+    }
+
+    async fn send(&self, packet: &Packet) -> Result<()> {
+        self.send_frame(&packet.to_frame()?).await
+    }
+
+    async fn send_frame(&self, frame: &Frame) -> Result<()> {
+        let mut transport = self.transport.lock().await;
+        frame.write(TransportWriter(&mut *transport)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StubTransport;
+
+    impl Transport for StubTransport {
+        async fn read(&mut self, _buf: &mut [u8]) -> usize {
+            unimplemented!()
+        }
+
+        async fn write(&mut self, _buf: &[u8]) {}
+    }
+
+    #[futures_test::test]
+    async fn read_mmr_rejects_an_address_with_another_read_already_in_flight() {
+        let client = De1Client::new(StubTransport);
+        client.mmr_slots.lock().await[0] = Some(0x1000);
+
+        assert_eq!(client.read_mmr(0x1000, 0).await, Err(Error::Unknown));
+    }
+
+    #[futures_test::test]
+    async fn handle_mmr_response_wakes_the_matching_slot() {
+        let client = De1Client::new(StubTransport);
+        let op = MmrOpperation {
+            len: 0,
+            addr: 0x1000,
+            data: [0u8; 16],
+        };
+        client.mmr_slots.lock().await[0] = Some(0x1000);
+        client.handle_mmr_response(op.clone()).await;
+
+        assert_eq!(client.mmr_signals[0].wait().await, op);
+    }
+}
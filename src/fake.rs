@@ -7,73 +7,342 @@ use embassy_sync::{
     pipe::{self},
 };
 use embassy_time::{Duration, Instant, Timer};
-use fixed::types::{U16F16, U4F12, U4F4, U8F8};
+use embedded_io_async::Write as _;
+use fixed::types::{U16F16, U4F12, U4F4, U7F1, U8F8};
 use heapless::Vec;
 use log::{error, info};
 
 use crate::{
+    firmware,
+    profile::MAX_FRAMES,
     serial::{Frame, LineReader},
-    Command, CommandFrame, Error, MmrOpperation, Packet, RequestedState, Result, ShotFrameWrite,
-    ShotHeaderWrite, ShotSample, ShotSettings, State, StateInfo, SubState, WaterLevels,
+    transport::{Transport, TransportWriter},
+    Calibration, Command, CommandFrame, Error, FwMapRequest, MmrOpperation, Packet,
+    RequestedState, Result, ShotFrameWrite, ShotHeaderWrite, ShotSample, ShotSettings, State,
+    StateInfo, SubState, Versions, WaterLevels,
 };
 
-const TICK_PERIOD: Duration = Duration::from_secs(1);
-#[derive(Default)]
+/// Pairs an in-process pipe reader/writer into the single `Transport` a
+/// `De1` expects, preserving the crate's existing test/emulator wiring.
+pub struct PipeTransport<'rx, 'tx> {
+    pub rx: pipe::Reader<'rx, NoopRawMutex, 256>,
+    pub tx: pipe::Writer<'tx, NoopRawMutex, 256>,
+}
+
+impl<'rx, 'tx> Transport for PipeTransport<'rx, 'tx> {
+    async fn read(&mut self, buf: &mut [u8]) -> usize {
+        self.rx.read(buf).await
+    }
+
+    async fn write(&mut self, buf: &[u8]) {
+        let _ = self.tx.write_all(buf).await;
+    }
+}
+
+/// Cadence at which the shot extraction simulation advances, independent of
+/// which notification streams (if any) are subscribed.
+const SIM_TICK_PERIOD: Duration = Duration::from_millis(100);
+
+/// A real DE1 streams `ShotSample`/`StateInfo` several times a second but
+/// `WaterLevels` only every few seconds; each subscription below drives its
+/// own cadence instead of sharing one tick.
+const SHOT_SAMPLE_PERIOD: Duration = Duration::from_millis(150);
+const STATE_INFO_PERIOD: Duration = Duration::from_millis(150);
+const WATER_LEVELS_PERIOD: Duration = Duration::from_secs(5);
+
+/// `ShotFrameWrite::flags` bit selecting flow control over pressure control.
+const FRAME_FLAG_CTRL_F: u8 = 0x01;
+/// `ShotFrameWrite::flags` bit enabling the trigger/exit comparison below.
+const FRAME_FLAG_DO_COMPARE: u8 = 0x02;
+/// `ShotFrameWrite::flags` bit selecting "exit when under" over "exit when over".
+const FRAME_FLAG_COMPARE_UNDER: u8 = 0x04;
+
+/// Simulated puck flow resistance, in bar per ml/s.
+const PUCK_RESISTANCE: f32 = 2.0;
+/// Time constant for `group_pressure`/`group_flow`/temperatures approaching
+/// their targets.
+const APPROACH_TIME_CONSTANT_S: f32 = 0.5;
+
+/// Moves `current` toward `target` over `dt_s` seconds, approximating a
+/// first-order system with time constant `APPROACH_TIME_CONSTANT_S`.
+fn approach(current: f32, target: f32, dt_s: f32) -> f32 {
+    let alpha = dt_s / (dt_s + APPROACH_TIME_CONSTANT_S);
+    current + (target - current) * alpha
+}
+
+/// A streaming notification's on/off state and the next instant it's due to
+/// fire, so the run loop can wake for whichever subscription needs it soonest
+/// instead of polling every subscription on one shared tick.
+struct Subscription {
+    enabled: bool,
+    period: Duration,
+    next_due: Instant,
+}
+
+impl Subscription {
+    const fn new(period: Duration) -> Self {
+        Self {
+            enabled: false,
+            period,
+            next_due: Instant::from_ticks(0),
+        }
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if enabled {
+            self.next_due = Instant::now();
+        }
+    }
+
+    /// Returns `true` (and schedules the next firing) if this subscription
+    /// is enabled and `now` has reached its due instant.
+    fn fire_if_due(&mut self, now: Instant) -> bool {
+        if self.enabled && now >= self.next_due {
+            self.next_due = now + self.period;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_due_if_enabled(&self) -> Option<Instant> {
+        self.enabled.then_some(self.next_due)
+    }
+}
+
 struct Subscriptions {
     mmr_read: bool,
-    shot_sample: bool,
-    state_info: bool,
-    water_levels: bool,
+    shot_sample: Subscription,
+    state_info: Subscription,
+    water_levels: Subscription,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self {
+            mmr_read: false,
+            shot_sample: Subscription::new(SHOT_SAMPLE_PERIOD),
+            state_info: Subscription::new(STATE_INFO_PERIOD),
+            water_levels: Subscription::new(WATER_LEVELS_PERIOD),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ShotFrame {
+    flags: u8,
+    set_value: U4F4,
+    temp: U7F1,
+    duration_s: f32,
+    trigger_value: U4F4,
+    max_volume: u16,
+}
+
+impl From<ShotFrameWrite> for ShotFrame {
+    fn from(value: ShotFrameWrite) -> Self {
+        Self {
+            flags: value.flags,
+            set_value: value.set_value,
+            temp: value.temp,
+            duration_s: value.frame_lenght.to_num(),
+            trigger_value: value.trigger_value,
+            max_volume: value.max_volume,
+        }
+    }
+}
+
+/// Tracks the uploaded profile and the extraction in progress, if any.
+#[derive(Default)]
+struct Shot {
+    frames: Vec<ShotFrame, MAX_FRAMES>,
+    preinfuse_frames: u8,
+    frame_number: usize,
+    frame_elapsed_s: f32,
+    volume_ml: f32,
+    group_pressure: U4F12,
+    group_flow: U4F12,
+    mix_temp: U8F8,
+    head_temp: U16F16,
+    running: bool,
+}
+
+/// Accumulates a chunked firmware upload: each `WriteToMmr` chunk lands at
+/// `addr - base_addr` in `buffer`; once a chunk ending exactly at
+/// `expected_len` (announced by the preceding `FwMapRequest`) lands, the
+/// trailing length/CRC record is checked against the buffer. Bytes received
+/// are not counted, so a gap left by a missing chunk is not detected.
+struct FirmwareTransfer {
+    base_addr: Option<u32>,
+    expected_len: usize,
+    buffer: [u8; firmware::MAX_TRANSFER_LEN],
+}
+
+impl Default for FirmwareTransfer {
+    fn default() -> Self {
+        Self {
+            base_addr: None,
+            expected_len: 0,
+            buffer: [0u8; firmware::MAX_TRANSFER_LEN],
+        }
+    }
 }
 
-pub struct De1<'rx, 'tx> {
-    rx_pipe: pipe::Reader<'rx, NoopRawMutex, 256>,
-    tx_pipe: pipe::Writer<'tx, NoopRawMutex, 256>,
+pub struct De1<T: Transport> {
+    transport: T,
     line_reader: LineReader<64>,
     subscriptions: Subscriptions,
+    next_sim_tick: Instant,
     timestamp: Wrapping<u16>,
+    reported_state: State,
+    shot: Shot,
+    firmware: FirmwareTransfer,
 }
 
-impl<'rx, 'tx> De1<'rx, 'tx> {
-    pub fn new(
-        rx_pipe: pipe::Reader<'rx, NoopRawMutex, 256>,
-        tx_pipe: pipe::Writer<'tx, NoopRawMutex, 256>,
-    ) -> Self {
+impl<T: Transport> De1<T> {
+    pub fn new(transport: T) -> Self {
         Self {
-            rx_pipe,
-            tx_pipe,
+            transport,
             line_reader: LineReader::new(),
             subscriptions: Default::default(),
+            next_sim_tick: Instant::now(),
             timestamp: Wrapping(0),
+            reported_state: State::Idle,
+            shot: Default::default(),
+            firmware: Default::default(),
         }
     }
 
+    /// The next instant `run` needs to wake for, across the shot simulation
+    /// and every enabled subscription.
+    fn next_wake(&self) -> Instant {
+        [
+            Some(self.next_sim_tick),
+            self.subscriptions.shot_sample.next_due_if_enabled(),
+            self.subscriptions.state_info.next_due_if_enabled(),
+            self.subscriptions.water_levels.next_due_if_enabled(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(self.next_sim_tick)
+    }
+
     pub async fn run(&mut self) -> ! {
-        let mut last_tick = Instant::now();
-        let mut buf = [0u8, 64];
+        let mut buf = [0u8; 64];
         loop {
-            let tick_target = last_tick + TICK_PERIOD;
+            let tick_target = self.next_wake();
 
-            let either = select(self.rx_pipe.read(&mut buf), Timer::at(tick_target)).await;
+            let either = select(self.transport.read(&mut buf), Timer::at(tick_target)).await;
 
             match either {
                 Either::First(read_len) => self.handle_read(&buf[..read_len]).await,
                 Either::Second(_) => {
-                    last_tick = tick_target;
-                    let _ = self.handle_tick().await;
+                    let _ = self.handle_tick(tick_target).await;
                 }
             }
         }
     }
 
+    async fn handle_versions(&mut self, value: Versions) -> Result<()> {
+        Ok(())
+    }
+
     async fn handle_requested_state(&mut self, value: RequestedState) -> Result<()> {
+        match value.state {
+            State::Espresso if !self.shot.frames.is_empty() => {
+                self.shot.frame_number = 0;
+                self.shot.frame_elapsed_s = 0.0;
+                self.shot.volume_ml = 0.0;
+                self.shot.running = true;
+                self.reported_state = State::Espresso;
+            }
+            State::Espresso => (),
+            _ => {
+                self.shot.running = false;
+                self.reported_state = value.state;
+            }
+        }
+
         Ok(())
     }
 
-    async fn send_command_packet<T: BinWrite>(&mut self, command: Command, data: &T) -> Result<()>
+    /// Advances the in-progress extraction by `dt_s` seconds: ramps
+    /// `group_pressure`/`group_flow` toward the current frame's target
+    /// through a simulated puck, integrates dispensed volume, ramps
+    /// temperatures, and steps to the next frame (or finishes) once the
+    /// frame's duration, trigger, or volume limit is reached.
+    fn advance_shot(&mut self, dt_s: f32) {
+        if !self.shot.running {
+            return;
+        }
+
+        let Some(frame) = self.shot.frames.get(self.shot.frame_number).copied() else {
+            self.finish_shot();
+            return;
+        };
+
+        let is_flow_control = frame.flags & FRAME_FLAG_CTRL_F != 0;
+        let target: f32 = frame.set_value.to_num();
+
+        if is_flow_control {
+            let flow = approach(self.shot.group_flow.to_num(), target, dt_s).clamp(0.0, 15.99);
+            self.shot.group_flow = U4F12::from_num(flow);
+            self.shot.group_pressure = U4F12::from_num((flow * PUCK_RESISTANCE).clamp(0.0, 15.99));
+        } else {
+            let pressure =
+                approach(self.shot.group_pressure.to_num(), target, dt_s).clamp(0.0, 15.99);
+            self.shot.group_pressure = U4F12::from_num(pressure);
+            self.shot.group_flow =
+                U4F12::from_num((pressure / PUCK_RESISTANCE).clamp(0.0, 15.99));
+        }
+
+        let flow: f32 = self.shot.group_flow.to_num();
+        self.shot.volume_ml += flow * dt_s;
+
+        let target_temp: f32 = frame.temp.to_num();
+        self.shot.mix_temp =
+            U8F8::from_num(approach(self.shot.mix_temp.to_num(), target_temp, dt_s));
+        self.shot.head_temp =
+            U16F16::from_num(approach(self.shot.head_temp.to_num(), target_temp, dt_s));
+
+        self.shot.frame_elapsed_s += dt_s;
+
+        let triggered = frame.flags & FRAME_FLAG_DO_COMPARE != 0 && {
+            let trigger: f32 = frame.trigger_value.to_num();
+            let compare_value: f32 = if is_flow_control {
+                self.shot.group_flow.to_num()
+            } else {
+                self.shot.group_pressure.to_num()
+            };
+            if frame.flags & FRAME_FLAG_COMPARE_UNDER != 0 {
+                compare_value < trigger
+            } else {
+                compare_value > trigger
+            }
+        };
+
+        let volume_exceeded =
+            frame.max_volume != 0 && self.shot.volume_ml >= frame.max_volume as f32;
+
+        if self.shot.frame_elapsed_s >= frame.duration_s || triggered || volume_exceeded {
+            self.shot.frame_number += 1;
+            self.shot.frame_elapsed_s = 0.0;
+            if self.shot.frame_number >= self.shot.frames.len() {
+                self.finish_shot();
+            }
+        }
+    }
+
+    fn finish_shot(&mut self) {
+        self.shot.running = false;
+        self.reported_state = State::Idle;
+    }
+
+    async fn send_command_packet<D: BinWrite>(&mut self, command: Command, data: &D) -> Result<()>
     where
-        T: WriteEndian,
-        for<'a> <T as BinWrite>::Args<'a>: Default,
+        D: WriteEndian,
+        for<'a> <D as BinWrite>::Args<'a>: Default,
     {
         let mut buf = [0u8; Command::MAX_DATA_LENGTH];
         data.write(&mut Cursor::new(&mut buf[..]))?;
@@ -82,7 +351,7 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
             data: Vec::from_slice(&buf[0..command.data_len()])?,
         });
 
-        frame.write(self.tx_pipe).await?;
+        frame.write(TransportWriter(&mut self.transport)).await?;
 
         Ok(())
     }
@@ -144,6 +413,58 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
     }
 
     async fn handle_write_to_mmr(&mut self, value: MmrOpperation) -> Result<()> {
+        let base_addr = *self.firmware.base_addr.get_or_insert(value.addr);
+        if value.addr < base_addr {
+            return Err(Error::FirmwareChunkOutOfRange(value.addr));
+        }
+        let offset = (value.addr - base_addr) as usize;
+        let chunk_len = (value.len as usize + 1) * 4;
+        let end = offset + chunk_len;
+
+        if chunk_len > value.data.len()
+            || end > self.firmware.expected_len
+            || end > firmware::MAX_TRANSFER_LEN
+        {
+            return Err(Error::FirmwareChunkOutOfRange(value.addr));
+        }
+
+        self.firmware.buffer[offset..end].copy_from_slice(&value.data[..chunk_len]);
+
+        if end == self.firmware.expected_len {
+            self.finish_firmware_transfer()?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the trailing length/CRC record a completed firmware transfer
+    /// ends with against the bytes actually accumulated.
+    fn finish_firmware_transfer(&mut self) -> Result<()> {
+        let Some(padded_image_len) = self
+            .firmware
+            .expected_len
+            .checked_sub(firmware::TRAILER_LEN)
+        else {
+            return Err(Error::FirmwareChecksumMismatch);
+        };
+        let trailer = &self.firmware.buffer[padded_image_len..self.firmware.expected_len];
+        let declared_len = u32::from_be_bytes(trailer[0..4].try_into().unwrap()) as usize;
+        let declared_crc = u32::from_be_bytes(trailer[4..8].try_into().unwrap());
+
+        if declared_len > padded_image_len {
+            return Err(Error::FirmwareChecksumMismatch);
+        }
+
+        let mut crc = 0xffff_ffffu32;
+        for &byte in &self.firmware.buffer[..declared_len] {
+            crc = firmware::crc32_update(crc, byte);
+        }
+
+        if declared_crc != !crc {
+            return Err(Error::FirmwareChecksumMismatch);
+        }
+
+        info!("FAKE: firmware transfer complete, {declared_len} bytes verified");
         Ok(())
     }
 
@@ -160,24 +481,43 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
     }
 
     async fn handle_shot_header_write(&mut self, value: ShotHeaderWrite) -> Result<()> {
+        self.shot.frames.clear();
+        self.shot.preinfuse_frames = value.preinfuse_frames;
+        self.shot.running = false;
         Ok(())
     }
 
     async fn handle_shot_frame_write(&mut self, value: ShotFrameWrite) -> Result<()> {
-        Ok(())
+        self.shot
+            .frames
+            .push(value.into())
+            .map_err(|_| Error::Unknown)
     }
 
     async fn handle_water_levels(&mut self, value: WaterLevels) -> Result<()> {
         Ok(())
     }
 
+    async fn handle_fw_map_request(&mut self, value: FwMapRequest) -> Result<()> {
+        self.firmware = FirmwareTransfer {
+            base_addr: None,
+            expected_len: (value.window_increment as usize + 1) * 4,
+            buffer: [0u8; firmware::MAX_TRANSFER_LEN],
+        };
+        Ok(())
+    }
+
+    async fn handle_calibration(&mut self, value: Calibration) -> Result<()> {
+        Ok(())
+    }
+
     async fn handle_subscription(&mut self, command: Command, enable: bool) -> Result<()> {
         info!("FAKE: subscription {:?} {}", command, enable);
         match command {
             Command::ReadFromMmr => self.subscriptions.mmr_read = enable,
-            Command::ShotSample => self.subscriptions.shot_sample = enable,
-            Command::StateInfo => self.subscriptions.state_info = enable,
-            Command::WaterLevels => self.subscriptions.water_levels = enable,
+            Command::ShotSample => self.subscriptions.shot_sample.set_enabled(enable),
+            Command::StateInfo => self.subscriptions.state_info.set_enabled(enable),
+            Command::WaterLevels => self.subscriptions.water_levels.set_enabled(enable),
             _ => (),
         }
 
@@ -190,20 +530,33 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
             return Err(Error::UnexpectedFrame);
         }
 
-        let packet: Packet = frame.try_into()?;
+        let packet = match frame {
+            Frame::FromDe1(command) | Frame::ToDe1(command) => Packet::from_command(&command)?,
+            Frame::Subscribe(c) => Packet::Subscribe(c),
+            Frame::Unsubscribe(c) => Packet::Unsubscribe(c),
+        };
 
         match packet {
+            Packet::Versions(val) => self.handle_versions(val).await?,
             Packet::RequestedState(val) => self.handle_requested_state(val).await?,
             Packet::ReadFromMmr(val) => self.handle_read_from_mmr(val).await?,
             Packet::WriteToMmr(val) => self.handle_write_to_mmr(val).await?,
+            Packet::FwMapRequest(val) => self.handle_fw_map_request(val).await?,
             Packet::ShotSettings(val) => self.handle_shot_settings(val).await?,
             Packet::ShotSample(val) => self.handle_shot_sample(val).await?,
             Packet::StateInfo(val) => self.handle_state_info(val).await?,
             Packet::ShotHeaderWrite(val) => self.handle_shot_header_write(val).await?,
             Packet::ShotFrameWrite(val) => self.handle_shot_frame_write(val).await?,
             Packet::WaterLevels(val) => self.handle_water_levels(val).await?,
-            Packet::Subscribe(c) => self.handle_subscription(c, true).await?,
-            Packet::Unsubscribe(c) => self.handle_subscription(c, false).await?,
+            Packet::Calibration(val) => self.handle_calibration(val).await?,
+            Packet::Subscribe(c) => {
+                let command = Command::from_serial_command(c).ok_or(Error::UnknownCommand(c))?;
+                self.handle_subscription(command, true).await?
+            }
+            Packet::Unsubscribe(c) => {
+                let command = Command::from_serial_command(c).ok_or(Error::UnknownCommand(c))?;
+                self.handle_subscription(command, false).await?
+            }
         }
 
         Ok(())
@@ -226,26 +579,45 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
 
     async fn send_shot_sample(&mut self) -> Result<()> {
         self.timestamp += 25;
+
+        let frame = self.shot.frames.get(self.shot.frame_number).copied();
+        let set_value = frame.map(|f| f.set_value).unwrap_or_default();
+        let set_temp = U8F8::from_num(frame.map(|f| f.temp.to_num::<f32>()).unwrap_or(0.0f32));
+        let is_flow_control = frame.map(|f| f.flags & FRAME_FLAG_CTRL_F != 0).unwrap_or(false);
+        let (set_group_pressure, set_group_flow) = if is_flow_control {
+            (U4F4::from_num(0), set_value)
+        } else {
+            (set_value, U4F4::from_num(0))
+        };
+
         let sample = ShotSample {
             timer: self.timestamp.0,
-            group_pressure: U4F12::from_num(0.0103),
-            group_flow: U4F12::from_num(1.8708),
-            mix_temp: U8F8::from_num(77.91),
-            head_temp: U16F16::from_num(85.79803),
-            set_mix_temp: U8F8::from_num(90),
-            set_head_temp: U8F8::from_num(90),
-            set_group_pressure: U4F4::from_num(0),
-            set_group_flow: U4F4::from_num(0),
-            frame_number: 5,
+            group_pressure: self.shot.group_pressure,
+            group_flow: self.shot.group_flow,
+            mix_temp: self.shot.mix_temp,
+            head_temp: self.shot.head_temp,
+            set_mix_temp: set_temp,
+            set_head_temp: set_temp,
+            set_group_pressure,
+            set_group_flow,
+            frame_number: self.shot.frame_number as u8,
             steam_temp: 158,
         };
         self.send_command_packet(Command::ShotSample, &sample).await
     }
 
     async fn send_state_info(&mut self) -> Result<()> {
+        let sub_state = if !self.shot.running {
+            SubState::NoState
+        } else if (self.shot.frame_number as u8) < self.shot.preinfuse_frames {
+            SubState::PreInfusion
+        } else {
+            SubState::Pouring
+        };
+
         let info = StateInfo {
-            state: State::Idle,
-            sub_state: SubState::NoState,
+            state: self.reported_state.clone(),
+            sub_state,
         };
         self.send_command_packet(Command::StateInfo, &info).await
     }
@@ -259,20 +631,203 @@ impl<'rx, 'tx> De1<'rx, 'tx> {
             .await
     }
 
-    async fn handle_tick(&mut self) -> Result<()> {
-        info!("FAKE: tick");
-        if self.subscriptions.shot_sample {
+    async fn handle_tick(&mut self, now: Instant) -> Result<()> {
+        if now >= self.next_sim_tick {
+            self.advance_shot(SIM_TICK_PERIOD.as_millis() as f32 / 1000.0);
+            self.next_sim_tick = now + SIM_TICK_PERIOD;
+        }
+
+        if self.subscriptions.shot_sample.fire_if_due(now) {
             self.send_shot_sample().await?
         }
 
-        if self.subscriptions.state_info {
+        if self.subscriptions.state_info.fire_if_due(now) {
             self.send_state_info().await?
         }
 
-        if self.subscriptions.water_levels {
+        if self.subscriptions.water_levels.fire_if_due(now) {
             self.send_water_levels().await?
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NullTransport;
+
+    impl Transport for NullTransport {
+        async fn read(&mut self, _buf: &mut [u8]) -> usize {
+            unimplemented!()
+        }
+
+        async fn write(&mut self, _buf: &[u8]) {}
+    }
+
+    fn shot_frame(set_value: f32, duration_s: f32) -> ShotFrame {
+        ShotFrame {
+            flags: 0,
+            set_value: U4F4::from_num(set_value),
+            temp: U7F1::from_num(93),
+            duration_s,
+            trigger_value: U4F4::from_num(0),
+            max_volume: 0,
+        }
+    }
+
+    #[test]
+    fn approach_closes_half_the_gap_in_one_time_constant_step() {
+        let v = approach(0.0, 10.0, APPROACH_TIME_CONSTANT_S);
+        assert!((v - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn advance_shot_steps_to_next_frame_once_duration_elapses() {
+        let mut de1 = De1::new(NullTransport);
+        de1.shot.frames.push(shot_frame(6.0, 0.05)).unwrap();
+        de1.shot.frames.push(shot_frame(9.0, 30.0)).unwrap();
+        de1.shot.running = true;
+
+        de1.advance_shot(0.1);
+
+        assert_eq!(de1.shot.frame_number, 1);
+    }
+
+    #[test]
+    fn advance_shot_finishes_after_the_last_frame() {
+        let mut de1 = De1::new(NullTransport);
+        de1.shot.frames.push(shot_frame(6.0, 0.05)).unwrap();
+        de1.shot.running = true;
+
+        de1.advance_shot(0.1);
+
+        assert!(!de1.shot.running);
+        assert_eq!(de1.reported_state, State::Idle);
+    }
+
+    #[test]
+    fn subscription_does_not_fire_until_enabled() {
+        let mut sub = Subscription::new(WATER_LEVELS_PERIOD);
+        assert!(!sub.fire_if_due(Instant::from_ticks(0)));
+        assert_eq!(sub.next_due_if_enabled(), None);
+    }
+
+    #[test]
+    fn subscription_fires_once_due_then_reschedules_a_full_period_out() {
+        let mut sub = Subscription::new(WATER_LEVELS_PERIOD);
+        sub.set_enabled(true);
+        let now = sub.next_due_if_enabled().unwrap();
+
+        assert!(sub.fire_if_due(now));
+        assert_eq!(sub.next_due_if_enabled(), Some(now + WATER_LEVELS_PERIOD));
+        assert!(!sub.fire_if_due(now));
+    }
+
+    #[test]
+    fn next_wake_is_the_soonest_of_the_sim_tick_and_enabled_subscriptions() {
+        let mut de1 = De1::new(NullTransport);
+        de1.subscriptions.water_levels.set_enabled(true);
+
+        let water_levels_due = de1.subscriptions.water_levels.next_due_if_enabled().unwrap();
+        assert_eq!(de1.next_wake(), de1.next_sim_tick.min(water_levels_due));
+    }
+
+    fn mmr_write(addr: u32, chunk: &[u8]) -> MmrOpperation {
+        let mut data = [0u8; 16];
+        data[..chunk.len()].copy_from_slice(chunk);
+        MmrOpperation {
+            len: (chunk.len() / 4 - 1) as u8,
+            addr,
+            data,
+        }
+    }
+
+    #[futures_test::test]
+    async fn firmware_transfer_round_trips_through_a_real_writer() {
+        let mut de1 = De1::new(NullTransport);
+        let image = [1u8, 2, 3, 4, 5, 6, 7];
+        let mut writer = firmware::FirmwareWriter::new(0x1000, 16, &image).unwrap();
+
+        de1.handle_fw_map_request(FwMapRequest {
+            window_increment: (writer.total_len() / 4 - 1) as u8,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        })
+        .await
+        .unwrap();
+
+        while let Some(Packet::WriteToMmr(op)) = writer.next_packet() {
+            de1.handle_write_to_mmr(op).await.unwrap();
+        }
+    }
+
+    #[futures_test::test]
+    async fn handle_write_to_mmr_rejects_a_chunk_longer_than_the_data_buffer() {
+        let mut de1 = De1::new(NullTransport);
+        de1.handle_fw_map_request(FwMapRequest {
+            window_increment: 255,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        })
+        .await
+        .unwrap();
+
+        // `len` of 4 claims a 20-byte chunk, longer than `data`'s 16 bytes.
+        let op = MmrOpperation {
+            len: 4,
+            addr: 0,
+            data: [0u8; 16],
+        };
+        assert!(matches!(
+            de1.handle_write_to_mmr(op).await,
+            Err(Error::FirmwareChunkOutOfRange(_))
+        ));
+    }
+
+    #[futures_test::test]
+    async fn handle_write_to_mmr_rejects_an_addr_before_the_transfers_base() {
+        let mut de1 = De1::new(NullTransport);
+        de1.handle_fw_map_request(FwMapRequest {
+            window_increment: 255,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        })
+        .await
+        .unwrap();
+
+        de1.handle_write_to_mmr(mmr_write(0x2000, &[0u8; 4]))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            de1.handle_write_to_mmr(mmr_write(0x1000, &[0u8; 4])).await,
+            Err(Error::FirmwareChunkOutOfRange(_))
+        ));
+    }
+
+    #[futures_test::test]
+    async fn finish_firmware_transfer_rejects_a_transfer_shorter_than_the_trailer() {
+        let mut de1 = De1::new(NullTransport);
+        // `window_increment == 0` announces a 4-byte transfer, shorter than
+        // the 8-byte trailer every transfer must end with.
+        de1.handle_fw_map_request(FwMapRequest {
+            window_increment: 0,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            de1.handle_write_to_mmr(mmr_write(0x1000, &[0u8; 4])).await,
+            Err(Error::FirmwareChecksumMismatch)
+        );
+    }
+}
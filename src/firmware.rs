@@ -0,0 +1,215 @@
+//! Chunked firmware-image upload over MMR writes: splits an image into
+//! fixed-size chunks, erases the target firmware slot first, and streams
+//! the chunks as ordered `WriteToMmr` operations followed by a trailer
+//! record (image length + CRC-32) the device checks once it has the whole
+//! image, the way chunked object stores reassemble fixed-size parts.
+
+use crate::{Error, FwMapRequest, MmrOpperation, Packet, Result};
+
+/// Largest chunk a single `WriteToMmr` can carry: the wire format's 16-byte
+/// `MmrOpperation::data` payload.
+pub const MAX_CHUNK_LEN: usize = 16;
+
+/// Bytes appended after the image: a big-endian `u32` length followed by a
+/// big-endian `u32` CRC-32 (IEEE 802.3) of the image.
+pub(crate) const TRAILER_LEN: usize = 8;
+
+/// Largest image (plus trailer) a transfer can describe: bounded by
+/// `FwMapRequest::window_increment` being a `u8` word count under the
+/// crate's `(n + 1) * 4` byte-count convention.
+pub const MAX_TRANSFER_LEN: usize = 256 * 4;
+
+pub(crate) fn padded_len(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+/// Streams `image` to `base_addr` in fixed-size chunks.
+pub struct FirmwareWriter<'a> {
+    base_addr: u32,
+    chunk_len: usize,
+    image: &'a [u8],
+    offset: usize,
+    crc: u32,
+    trailer_sent: bool,
+}
+
+impl<'a> FirmwareWriter<'a> {
+    /// `chunk_len` must be a nonzero multiple of 4 no larger than
+    /// `MAX_CHUNK_LEN`; `base_addr` must be 4-byte aligned and the image
+    /// (plus its trailer) must fit within `MAX_TRANSFER_LEN`.
+    pub fn new(base_addr: u32, chunk_len: usize, image: &'a [u8]) -> Result<Self> {
+        if chunk_len == 0 || chunk_len > MAX_CHUNK_LEN || !chunk_len.is_multiple_of(4) {
+            return Err(Error::Unknown);
+        }
+        if !base_addr.is_multiple_of(4) {
+            return Err(Error::Unknown);
+        }
+
+        let writer = Self {
+            base_addr,
+            chunk_len,
+            image,
+            offset: 0,
+            crc: 0xffff_ffff,
+            trailer_sent: false,
+        };
+        if writer.total_len() > MAX_TRANSFER_LEN {
+            return Err(Error::FirmwareChunkOutOfRange(
+                base_addr + MAX_TRANSFER_LEN as u32,
+            ));
+        }
+
+        Ok(writer)
+    }
+
+    /// The erase-before-write request for the target firmware slot; send
+    /// this once, before any chunk.
+    pub fn erase_packet(&self) -> Packet {
+        let total_words = self.total_len() / 4;
+        Packet::FwMapRequest(FwMapRequest {
+            window_increment: (total_words - 1) as u8,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        })
+    }
+
+    /// Total bytes this transfer will write: the image, rounded up to a
+    /// whole number of words, plus the trailer.
+    pub fn total_len(&self) -> usize {
+        padded_len(self.image.len()) + TRAILER_LEN
+    }
+
+    /// Bytes of the image written so far (excludes the trailer).
+    pub fn bytes_written(&self) -> usize {
+        self.offset
+    }
+
+    /// Builds the next `WriteToMmr` operation, or `None` once the image and
+    /// its trailer have both been sent.
+    pub fn next_packet(&mut self) -> Option<Packet> {
+        if self.offset < self.image.len() {
+            return Some(self.next_chunk_packet());
+        }
+        if !self.trailer_sent {
+            self.trailer_sent = true;
+            return Some(self.trailer_packet());
+        }
+        None
+    }
+
+    fn next_chunk_packet(&mut self) -> Packet {
+        let end = (self.offset + self.chunk_len).min(self.image.len());
+        let chunk = &self.image[self.offset..end];
+
+        for &byte in chunk {
+            self.crc = crc32_update(self.crc, byte);
+        }
+
+        let mut data = [0u8; MAX_CHUNK_LEN];
+        data[..chunk.len()].copy_from_slice(chunk);
+        let declared_len = padded_len(chunk.len());
+
+        let packet = Packet::WriteToMmr(MmrOpperation {
+            len: (declared_len / 4 - 1) as u8,
+            addr: self.base_addr + self.offset as u32,
+            data,
+        });
+
+        self.offset = end;
+        packet
+    }
+
+    fn trailer_packet(&self) -> Packet {
+        let mut data = [0u8; MAX_CHUNK_LEN];
+        data[..4].copy_from_slice(&(self.image.len() as u32).to_be_bytes());
+        data[4..8].copy_from_slice(&(!self.crc).to_be_bytes());
+
+        Packet::WriteToMmr(MmrOpperation {
+            len: (TRAILER_LEN / 4 - 1) as u8,
+            addr: self.base_addr + padded_len(self.image.len()) as u32,
+            data,
+        })
+    }
+}
+
+/// CRC-32 (IEEE 802.3), one byte at a time.
+pub(crate) fn crc32_update(crc: u32, byte: u8) -> u32 {
+    let mut crc = crc ^ byte as u32;
+    for _ in 0..8 {
+        crc = if crc & 1 != 0 {
+            (crc >> 1) ^ 0xedb8_8320
+        } else {
+            crc >> 1
+        };
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_rejects_a_chunk_len_that_is_not_a_multiple_of_four() {
+        assert_eq!(
+            FirmwareWriter::new(0, 3, &[0u8; 4]).err(),
+            Some(Error::Unknown)
+        );
+    }
+
+    #[test]
+    fn new_rejects_an_unaligned_base_addr() {
+        assert_eq!(
+            FirmwareWriter::new(2, 4, &[0u8; 4]).err(),
+            Some(Error::Unknown)
+        );
+    }
+
+    #[test]
+    fn new_rejects_an_image_too_large_to_fit_a_transfer() {
+        let image = [0u8; MAX_TRANSFER_LEN];
+        assert!(matches!(
+            FirmwareWriter::new(0, MAX_CHUNK_LEN, &image).err(),
+            Some(Error::FirmwareChunkOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn next_packet_streams_chunks_then_the_trailer_then_ends() {
+        let image = [1u8, 2, 3, 4, 5];
+        let mut writer = FirmwareWriter::new(0x1000, 4, &image).unwrap();
+
+        let Some(Packet::WriteToMmr(first)) = writer.next_packet() else {
+            panic!("expected the first chunk");
+        };
+        assert_eq!(first.addr, 0x1000);
+        assert_eq!(first.data[..4], image[..4]);
+
+        let Some(Packet::WriteToMmr(second)) = writer.next_packet() else {
+            panic!("expected the final (padded) chunk");
+        };
+        assert_eq!(second.addr, 0x1004);
+        assert_eq!(second.data[0], image[4]);
+
+        let Some(Packet::WriteToMmr(trailer)) = writer.next_packet() else {
+            panic!("expected the trailer");
+        };
+        assert_eq!(trailer.addr, 0x1000 + padded_len(image.len()) as u32);
+        assert_eq!(
+            u32::from_be_bytes(trailer.data[0..4].try_into().unwrap()),
+            image.len() as u32
+        );
+
+        assert!(writer.next_packet().is_none());
+    }
+
+    #[test]
+    fn crc32_update_matches_a_known_vector() {
+        let mut crc = 0xffff_ffffu32;
+        for &byte in b"123456789" {
+            crc = crc32_update(crc, byte);
+        }
+        assert_eq!(!crc, 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,68 @@
+//! Binary GATT characteristic framing, alongside the ASCII-hex serial
+//! framing in `serial`.
+//!
+//! A BLE client exchanges raw characteristic payloads keyed by a 16-bit
+//! UUID suffix (`Command::gatt_uu8d`) with no `[X]`/`<X>` brackets and no
+//! hex encoding, so the `serial` line parser doesn't apply; this module
+//! decodes/encodes `Packet`s directly against those bytes instead.
+
+use heapless::Vec;
+
+use crate::{Command, CommandFrame, Error, Packet, Result};
+
+impl Packet {
+    /// Decodes a GATT characteristic write/notification. `uuid` selects the
+    /// command via `Command::gatt_uu8d` and `data` is the raw, unframed
+    /// payload exactly `command.data_len()` bytes long.
+    pub fn from_gatt(uuid: u16, data: &[u8]) -> Result<Self> {
+        let command = Command::from_gatt_uuid(uuid).ok_or(Error::Unknown)?;
+        if data.len() != command.data_len() {
+            return Err(Error::Unknown);
+        }
+
+        let command_frame = CommandFrame {
+            command: command.serial_command(),
+            data: Vec::from_slice(data).map_err(|_| Error::Unknown)?,
+        };
+        Self::from_command(&command_frame)
+    }
+
+    /// Encodes this packet as a `(uuid, data)` pair ready to write to its
+    /// GATT characteristic.
+    pub fn to_gatt(&self) -> Result<(u16, Vec<u8, { Command::MAX_DATA_LENGTH }>)> {
+        let command_frame = self.to_command_frame()?;
+        let command = Command::from_serial_command(command_frame.command).ok_or(Error::Unknown)?;
+        Ok((command.gatt_uu8d(), command_frame.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fixed::types::U8F8;
+
+    use super::*;
+    use crate::WaterLevels;
+
+    #[test]
+    fn to_gatt_round_trips_through_from_gatt() {
+        let packet = Packet::WaterLevels(WaterLevels {
+            level: U8F8::from_num(13.5),
+            start_fill_level: U8F8::from_num(5),
+        });
+        let (uuid, data) = packet.to_gatt().unwrap();
+        assert_eq!(Packet::from_gatt(uuid, &data).unwrap(), packet);
+    }
+
+    #[test]
+    fn from_gatt_rejects_unknown_uuid() {
+        assert_eq!(Packet::from_gatt(0xffff, &[]), Err(Error::Unknown));
+    }
+
+    #[test]
+    fn from_gatt_rejects_wrong_length_payload() {
+        assert_eq!(
+            Packet::from_gatt(Command::WaterLevels.gatt_uu8d(), &[0u8]),
+            Err(Error::Unknown)
+        );
+    }
+}
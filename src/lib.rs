@@ -4,8 +4,9 @@ use core::fmt::Debug;
 use core::str::FromStr;
 
 use binrw::{
-    binrw, // #[binrw] attribute
+    binrw,          // #[binrw] attribute
     io::Cursor,
+    meta::WriteEndian,
     BinRead,  // trait for reading
     BinWrite, // trait for writing
 };
@@ -13,8 +14,17 @@ use fixed::{
     traits::LossyFrom,
     types::{U16F16, U4F12, U4F4, U7F1, U8F24, U8F8},
 };
-
+use heapless::Vec;
+
+pub mod client;
+pub mod de1_client;
+pub mod fake;
+pub mod firmware;
+pub mod gatt;
+pub mod mmr;
+pub mod profile;
 pub mod serial;
+pub mod transport;
 
 pub use serial::{CommandFrame, Frame};
 
@@ -23,6 +33,23 @@ pub enum Error {
     ParseError,
     BinRwError,
     UnknownCommand(char),
+    /// A catch-all for malformed input that doesn't warrant its own variant,
+    /// e.g. a buffer too small for the data it's asked to hold.
+    Unknown,
+    /// The underlying transport failed to write a frame.
+    IoError,
+    /// A `ReadFromMmr`/`WriteToMmr` addressed a register this crate doesn't
+    /// know about.
+    UnsupportedMmr(u32),
+    /// A firmware chunk's address or length falls outside the transfer
+    /// announced by the preceding `FwMapRequest`.
+    FirmwareChunkOutOfRange(u32),
+    /// A completed firmware transfer's trailing length/CRC record didn't
+    /// match the bytes actually received.
+    FirmwareChecksumMismatch,
+    /// A frame arrived going the wrong direction for the context it was
+    /// received in.
+    UnexpectedFrame,
 }
 
 impl From<binrw::Error> for Error {
@@ -31,6 +58,12 @@ impl From<binrw::Error> for Error {
     }
 }
 
+impl From<()> for Error {
+    fn from(_value: ()) -> Self {
+        Self::Unknown
+    }
+}
+
 type Result<T> = core::result::Result<T, Error>;
 
 fn read_u24(val: [u8; 3]) -> u32 {
@@ -95,6 +128,10 @@ pub enum Command {
 }
 
 impl Command {
+    /// Largest `data_len()` across all commands, used to size the scratch
+    /// buffer in `Packet::encode_command_frame`.
+    pub const MAX_DATA_LENGTH: usize = 20;
+
     pub const fn serial_command(&self) -> char {
         match self {
             Command::Versions => 'A',
@@ -129,6 +166,42 @@ impl Command {
         }
     }
 
+    pub const fn from_serial_command(c: char) -> Option<Command> {
+        match c {
+            'A' => Some(Command::Versions),
+            'B' => Some(Command::RequestedState),
+            'E' => Some(Command::ReadFromMmr),
+            'F' => Some(Command::WriteToMmr),
+            'I' => Some(Command::FwMapRequest),
+            'K' => Some(Command::ShotSettings),
+            'M' => Some(Command::ShotSample),
+            'N' => Some(Command::StateInfo),
+            'O' => Some(Command::HeaderWrite),
+            'P' => Some(Command::FrameWrite),
+            'Q' => Some(Command::WaterLevels),
+            'R' => Some(Command::Calibration),
+            _ => None,
+        }
+    }
+
+    pub const fn from_gatt_uuid(uuid: u16) -> Option<Command> {
+        match uuid {
+            0xa001 => Some(Command::Versions),
+            0xa002 => Some(Command::RequestedState),
+            0xa005 => Some(Command::ReadFromMmr),
+            0xa006 => Some(Command::WriteToMmr),
+            0xa009 => Some(Command::FwMapRequest),
+            0xa00b => Some(Command::ShotSettings),
+            0xa00d => Some(Command::ShotSample),
+            0xa00e => Some(Command::StateInfo),
+            0xa00f => Some(Command::HeaderWrite),
+            0xa010 => Some(Command::FrameWrite),
+            0xa011 => Some(Command::WaterLevels),
+            0xa012 => Some(Command::Calibration),
+            _ => None,
+        }
+    }
+
     pub const fn data_len(&self) -> usize {
         match self {
             Command::Versions => 18,
@@ -152,7 +225,7 @@ impl Command {
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[br(repr = u8)]
 #[bw(repr = u8)]
-enum State {
+pub enum State {
     Sleep = 0x00,
     GoingToSleep = 0x01,
     Idle = 0x02,
@@ -269,7 +342,16 @@ pub struct MmrOpperation {
 //     }
 // }
 
-pub struct FwMapRequest {}
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FwMapRequest {
+    window_increment: u8,
+    fw_to_erase: u8,
+    fw_to_map: u8,
+    first_error: u32,
+}
+
 pub struct Temperatures {}
 
 #[binrw]
@@ -397,19 +479,40 @@ pub struct WaterLevels {
     start_fill_level: U8F8,
 }
 
-pub struct Calibration {}
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Calibration {
+    calibration_target: u8,
+    flags: u8,
+
+    #[br(map = |val: u32| U16F16::from_bits(val))]
+    #[bw(map = |val| val.to_bits())]
+    measured: U16F16,
+
+    #[br(map = |val: u32| U16F16::from_bits(val))]
+    #[bw(map = |val| val.to_bits())]
+    written: U16F16,
+
+    #[br(map = |val: u32| U16F16::from_bits(val))]
+    #[bw(map = |val| val.to_bits())]
+    uncalibrated: U16F16,
+}
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Packet {
+    Versions(Versions),
     RequestedState(RequestedState),
     ReadFromMmr(MmrOpperation),
     WriteToMmr(MmrOpperation),
+    FwMapRequest(FwMapRequest),
     ShotSettings(ShotSettings),
     ShotSample(ShotSample),
     StateInfo(StateInfo),
     ShotHeaderWrite(ShotHeaderWrite),
     ShotFrameWrite(ShotFrameWrite),
     WaterLevels(WaterLevels),
+    Calibration(Calibration),
     Subscribe(char),
     Unsubscribe(char),
 }
@@ -417,6 +520,10 @@ pub enum Packet {
 impl Packet {
     fn from_command(command: &CommandFrame) -> Result<Self> {
         match command.command {
+            'A' => {
+                let versions = Versions::read(&mut Cursor::new(&command.data))?;
+                Ok(Self::Versions(versions))
+            }
             'B' => {
                 let state = RequestedState::read(&mut Cursor::new(&command.data))?;
                 Ok(Self::RequestedState(state))
@@ -453,9 +560,75 @@ impl Packet {
                 let water_levels = WaterLevels::read(&mut Cursor::new(&command.data))?;
                 Ok(Self::WaterLevels(water_levels))
             }
+            'R' => {
+                let calibration = Calibration::read(&mut Cursor::new(&command.data))?;
+                Ok(Self::Calibration(calibration))
+            }
+            'I' => {
+                let fw_map_request = FwMapRequest::read(&mut Cursor::new(&command.data))?;
+                Ok(Self::FwMapRequest(fw_map_request))
+            }
             _ => Err(Error::UnknownCommand(command.command)),
         }
     }
+
+    /// Encodes a single `BinWrite`-able payload into a `CommandFrame` for
+    /// `command`, filling `data` from the type's `binrw` layout.
+    fn encode_command_frame<T>(command: Command, value: &T) -> Result<CommandFrame>
+    where
+        T: BinWrite + WriteEndian,
+        for<'a> <T as BinWrite>::Args<'a>: Default,
+    {
+        let mut buf = [0u8; Command::MAX_DATA_LENGTH];
+        value.write(&mut Cursor::new(&mut buf[..]))?;
+        let data = Vec::from_slice(&buf[..command.data_len()]).map_err(|_| Error::Unknown)?;
+        Ok(CommandFrame {
+            command: command.serial_command(),
+            data,
+        })
+    }
+
+    /// Encodes this packet's payload into a `CommandFrame`, the inverse of
+    /// `from_command`.
+    pub fn to_command_frame(&self) -> Result<CommandFrame> {
+        match self {
+            Self::Versions(v) => Self::encode_command_frame(Command::Versions, v),
+            Self::RequestedState(v) => Self::encode_command_frame(Command::RequestedState, v),
+            Self::ReadFromMmr(v) => Self::encode_command_frame(Command::ReadFromMmr, v),
+            Self::WriteToMmr(v) => Self::encode_command_frame(Command::WriteToMmr, v),
+            Self::FwMapRequest(v) => Self::encode_command_frame(Command::FwMapRequest, v),
+            Self::ShotSettings(v) => Self::encode_command_frame(Command::ShotSettings, v),
+            Self::ShotSample(v) => Self::encode_command_frame(Command::ShotSample, v),
+            Self::StateInfo(v) => Self::encode_command_frame(Command::StateInfo, v),
+            Self::ShotHeaderWrite(v) => Self::encode_command_frame(Command::HeaderWrite, v),
+            Self::ShotFrameWrite(v) => Self::encode_command_frame(Command::FrameWrite, v),
+            Self::WaterLevels(v) => Self::encode_command_frame(Command::WaterLevels, v),
+            Self::Calibration(v) => Self::encode_command_frame(Command::Calibration, v),
+            Self::Subscribe(_) | Self::Unsubscribe(_) => Err(Error::Unknown),
+        }
+    }
+
+    /// Encodes this packet into a wire `Frame`, the inverse of `FromStr` /
+    /// `from_command`. Subscribe/unsubscribe packets map directly onto their
+    /// `<+X>` / `<-X>` frames; everything else is wrapped in a `CommandFrame`
+    /// and tagged with the direction the machine actually sends it in.
+    pub fn to_frame(&self) -> Result<Frame> {
+        match self {
+            Self::Subscribe(c) => Ok(Frame::Subscribe(*c)),
+            Self::Unsubscribe(c) => Ok(Frame::Unsubscribe(*c)),
+            Self::Versions(_) | Self::ShotSample(_) | Self::StateInfo(_) | Self::WaterLevels(_) => {
+                Ok(Frame::FromDe1(self.to_command_frame()?))
+            }
+            Self::RequestedState(_)
+            | Self::ReadFromMmr(_)
+            | Self::WriteToMmr(_)
+            | Self::FwMapRequest(_)
+            | Self::ShotSettings(_)
+            | Self::ShotHeaderWrite(_)
+            | Self::ShotFrameWrite(_)
+            | Self::Calibration(_) => Ok(Frame::ToDe1(self.to_command_frame()?)),
+        }
+    }
 }
 
 impl FromStr for Packet {
@@ -476,11 +649,100 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test() {
-        let packet = "[M]5F380000000058DA59C2E645F55A00000000A0"
-            .parse::<Packet>()
-            .unwrap();
-        println!("{packet:?}");
-        assert!(false);
+    fn to_command_frame_round_trips_through_from_command() {
+        let packet = Packet::WaterLevels(WaterLevels {
+            level: U8F8::from_num(13.5),
+            start_fill_level: U8F8::from_num(5),
+        });
+        let frame = packet.to_command_frame().unwrap();
+        assert_eq!(Packet::from_command(&frame).unwrap(), packet);
+    }
+
+    #[test]
+    fn to_frame_tags_de1_originated_packets_as_from_de1() {
+        let packet = Packet::StateInfo(StateInfo {
+            state: State::Idle,
+            sub_state: SubState::NoState,
+        });
+        assert!(matches!(packet.to_frame().unwrap(), Frame::FromDe1(_)));
+    }
+
+    #[test]
+    fn to_frame_tags_controller_originated_packets_as_to_de1() {
+        let packet = Packet::RequestedState(RequestedState {
+            state: State::Espresso,
+        });
+        assert!(matches!(packet.to_frame().unwrap(), Frame::ToDe1(_)));
+    }
+
+    #[test]
+    fn to_frame_round_trips_subscribe_and_unsubscribe() {
+        assert_eq!(
+            Packet::Subscribe('M').to_frame().unwrap(),
+            Frame::Subscribe('M')
+        );
+        assert_eq!(
+            Packet::Unsubscribe('M').to_frame().unwrap(),
+            Frame::Unsubscribe('M')
+        );
+    }
+
+    #[test]
+    fn versions_packet_round_trips_through_from_command() {
+        let packet = Packet::Versions(Versions {
+            bluetooth: Version {
+                api_version: 1,
+                release: 2,
+                commits: 3,
+                changes: 4,
+                sha: 5,
+            },
+            firmware: Version {
+                api_version: 6,
+                release: 7,
+                commits: 8,
+                changes: 9,
+                sha: 10,
+            },
+        });
+        let frame = packet.to_command_frame().unwrap();
+        assert_eq!(Packet::from_command(&frame).unwrap(), packet);
+    }
+
+    #[test]
+    fn calibration_packet_round_trips_through_from_command() {
+        let packet = Packet::Calibration(Calibration {
+            calibration_target: 1,
+            flags: 2,
+            measured: U16F16::from_num(3.5),
+            written: U16F16::from_num(4.5),
+            uncalibrated: U16F16::from_num(5.5),
+        });
+        let frame = packet.to_command_frame().unwrap();
+        assert_eq!(Packet::from_command(&frame).unwrap(), packet);
+    }
+
+    #[test]
+    fn fw_map_request_packet_round_trips_through_from_command() {
+        let packet = Packet::FwMapRequest(FwMapRequest {
+            window_increment: 63,
+            fw_to_erase: 1,
+            fw_to_map: 1,
+            first_error: 0,
+        });
+        let frame = packet.to_command_frame().unwrap();
+        assert_eq!(Packet::from_command(&frame).unwrap(), packet);
+    }
+
+    #[test]
+    fn to_command_frame_rejects_subscribe_and_unsubscribe() {
+        assert_eq!(
+            Packet::Subscribe('M').to_command_frame(),
+            Err(Error::Unknown)
+        );
+        assert_eq!(
+            Packet::Unsubscribe('M').to_command_frame(),
+            Err(Error::Unknown)
+        );
     }
 }
@@ -0,0 +1,194 @@
+//! Typed register map for the DE1's memory-mapped register (MMR) interface.
+//!
+//! `ReadFromMmr`/`WriteToMmr` only know about a raw 24-bit `addr` and a
+//! 16-byte `data` blob; this module names the registers the machine
+//! actually documents so callers don't have to hand-assemble byte layouts
+//! for every read or write.
+
+use binrw::{binrw, io::Cursor, BinRead};
+
+use crate::{Error, MmrOpperation, Result};
+
+/// A documented DE1 MMR register: a fixed base address and payload width.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Register {
+    /// GHC (group head controller) presence/version info.
+    GhcInfo,
+    /// Fan-on temperature threshold.
+    FanThreshold,
+    /// Steam heater PID/ramp settings.
+    SteamHeaterSettings,
+    /// Machine serial number.
+    SerialNumber,
+    /// Last firmware fault's error code and source line.
+    FirmwareErrorData,
+}
+
+impl Register {
+    /// The register's base address.
+    pub const fn addr(&self) -> u32 {
+        match self {
+            Register::GhcInfo => 0x800008,
+            Register::SteamHeaterSettings => 0x803810,
+            Register::FanThreshold => 0x80381c,
+            Register::SerialNumber => 0x803830,
+            Register::FirmwareErrorData => 0x803834,
+        }
+    }
+
+    /// Width of the register's payload, in bytes. Always a multiple of 4
+    /// and no larger than `MmrOpperation::data`.
+    pub const fn width(&self) -> usize {
+        match self {
+            Register::GhcInfo => 12,
+            Register::SteamHeaterSettings => 12,
+            Register::FanThreshold => 4,
+            Register::SerialNumber => 4,
+            Register::FirmwareErrorData => 8,
+        }
+    }
+
+    /// `MmrOpperation::len` for this register, i.e. `width / 4 - 1` per the
+    /// device's `(len + 1) * 4` byte-count convention.
+    const fn len(&self) -> u8 {
+        (self.width() / 4 - 1) as u8
+    }
+}
+
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GhcInfo {
+    pub flags: u32,
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u32,
+}
+
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SteamHeaterSettings {
+    pub target_temp: u32,
+    pub ramp_rate: u32,
+    pub hold_time: u32,
+}
+
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FirmwareErrorData {
+    pub error_code: u32,
+    pub line: u32,
+}
+
+impl MmrOpperation {
+    /// Builds a read request for `register`.
+    pub fn read_register(register: Register) -> Result<Self> {
+        Self::check_alignment(register)?;
+        Ok(Self {
+            len: register.len(),
+            addr: register.addr(),
+            data: [0u8; 16],
+        })
+    }
+
+    /// Builds a write request for `register`, with `value` as the raw
+    /// big-endian payload. `value` must be exactly `register.width()` bytes.
+    pub fn write_register(register: Register, value: &[u8]) -> Result<Self> {
+        Self::check_alignment(register)?;
+        if value.len() != register.width() {
+            return Err(Error::Unknown);
+        }
+
+        let mut data = [0u8; 16];
+        data[..value.len()].copy_from_slice(value);
+        Ok(Self {
+            len: register.len(),
+            addr: register.addr(),
+            data,
+        })
+    }
+
+    fn check_alignment(register: Register) -> Result<()> {
+        if !register.addr().is_multiple_of(4) {
+            return Err(Error::Unknown);
+        }
+        if register.width() > 16 {
+            return Err(Error::Unknown);
+        }
+        Ok(())
+    }
+
+    /// Decodes this operation's `data` as `GhcInfo`.
+    pub fn as_ghc_info(&self) -> Result<GhcInfo> {
+        Ok(GhcInfo::read(&mut Cursor::new(
+            &self.data[..Register::GhcInfo.width()],
+        ))?)
+    }
+
+    /// Decodes this operation's `data` as a fan threshold temperature.
+    pub fn as_fan_threshold(&self) -> u32 {
+        u32::from_be_bytes(self.data[..4].try_into().unwrap())
+    }
+
+    /// Decodes this operation's `data` as `SteamHeaterSettings`.
+    pub fn as_steam_heater_settings(&self) -> Result<SteamHeaterSettings> {
+        Ok(SteamHeaterSettings::read(&mut Cursor::new(
+            &self.data[..Register::SteamHeaterSettings.width()],
+        ))?)
+    }
+
+    /// Decodes this operation's `data` as a serial number.
+    pub fn as_serial_number(&self) -> u32 {
+        u32::from_be_bytes(self.data[..4].try_into().unwrap())
+    }
+
+    /// Decodes this operation's `data` as `FirmwareErrorData`.
+    pub fn as_firmware_error_data(&self) -> Result<FirmwareErrorData> {
+        Ok(FirmwareErrorData::read(&mut Cursor::new(
+            &self.data[..Register::FirmwareErrorData.width()],
+        ))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_register_sets_addr_and_len() {
+        let op = MmrOpperation::read_register(Register::SerialNumber).unwrap();
+        assert_eq!(op.addr, Register::SerialNumber.addr());
+        assert_eq!(op.len, Register::SerialNumber.len());
+    }
+
+    #[test]
+    fn write_register_round_trips_as_fan_threshold() {
+        let op = MmrOpperation::write_register(Register::FanThreshold, &[0x12, 0x34, 0x56, 0x78])
+            .unwrap();
+        assert_eq!(op.as_fan_threshold(), 0x12345678);
+    }
+
+    #[test]
+    fn write_register_rejects_mismatched_value_len() {
+        assert_eq!(
+            MmrOpperation::write_register(Register::FanThreshold, &[0x00]),
+            Err(Error::Unknown)
+        );
+    }
+
+    #[test]
+    fn as_ghc_info_decodes_width_prefix_of_data() {
+        let op = MmrOpperation::write_register(
+            Register::GhcInfo,
+            &[0, 0, 0, 1, 0, 2, 0, 3, 0, 0, 0, 4],
+        )
+        .unwrap();
+        let info = op.as_ghc_info().unwrap();
+        assert_eq!(info.flags, 1);
+        assert_eq!(info.major, 2);
+        assert_eq!(info.minor, 3);
+        assert_eq!(info.patch, 4);
+    }
+}
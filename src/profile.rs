@@ -0,0 +1,149 @@
+//! Builds a complete espresso shot profile — a header plus its ordered
+//! frames — ready to upload to the DE1 as one `ShotHeaderWrite` followed by
+//! `frames` many `ShotFrameWrite`s.
+
+use fixed::types::{U4F4, U7F1, U8F24};
+use heapless::Vec;
+
+use crate::{read_f817, write_f817, Error, Packet, Result, ShotFrameWrite, ShotHeaderWrite};
+
+/// Largest number of frames a profile may hold. The DE1 firmware itself
+/// doesn't document a hard cap; this is generous enough for any real
+/// profile while keeping `Profile` stack-allocated.
+pub const MAX_FRAMES: usize = 20;
+
+/// One step of a shot: a pressure- or flow-control target, temperature,
+/// duration, exit trigger, and volume limit. Mirrors `ShotFrameWrite`
+/// without the wire-level `index`, which `Profile` assigns automatically.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameDescriptor {
+    pub flags: u8,
+    pub set_value: U4F4,
+    pub temp: U7F1,
+    pub duration: U8F24,
+    pub trigger_value: U4F4,
+    pub max_volume: u16,
+}
+
+/// A complete shot profile: header parameters plus its ordered frames.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    version: u8,
+    minimum_pressure: U4F4,
+    minimum_flow: U4F4,
+    preinfuse_frames: u8,
+    frames: Vec<FrameDescriptor, MAX_FRAMES>,
+}
+
+impl Profile {
+    pub fn new(version: u8, minimum_pressure: U4F4, minimum_flow: U4F4) -> Self {
+        Self {
+            version,
+            minimum_pressure,
+            minimum_flow,
+            preinfuse_frames: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Appends a frame, verifying its `duration` survives the `f817`
+    /// round trip (values <= 12.7s keep tenth-second resolution, larger
+    /// values fall back to whole seconds).
+    pub fn push_frame(&mut self, frame: FrameDescriptor) -> Result<()> {
+        if read_f817(write_f817(&frame.duration)) != frame.duration {
+            return Err(Error::Unknown);
+        }
+
+        self.frames.push(frame).map_err(|_| Error::Unknown)
+    }
+
+    /// Marks the first `preinfuse_frames` pushed frames as preinfusion.
+    /// Must not exceed the number of frames already pushed.
+    pub fn set_preinfuse_frames(&mut self, preinfuse_frames: u8) -> Result<()> {
+        if preinfuse_frames as usize > self.frames.len() {
+            return Err(Error::Unknown);
+        }
+
+        self.preinfuse_frames = preinfuse_frames;
+        Ok(())
+    }
+
+    /// Yields the `ShotHeaderWrite` followed by each `ShotFrameWrite`, with
+    /// `index` and `frames` filled in automatically.
+    pub fn to_packets(&self) -> impl Iterator<Item = Packet> + '_ {
+        let header = Packet::ShotHeaderWrite(ShotHeaderWrite {
+            version: self.version,
+            frames: self.frames.len() as u8,
+            preinfuse_frames: self.preinfuse_frames,
+            minimum_pressure: self.minimum_pressure,
+            minimum_flow: self.minimum_flow,
+        });
+
+        core::iter::once(header).chain(self.frames.iter().enumerate().map(|(index, frame)| {
+            Packet::ShotFrameWrite(ShotFrameWrite {
+                index: index as u8,
+                flags: frame.flags,
+                set_value: frame.set_value,
+                temp: frame.temp,
+                frame_lenght: frame.duration,
+                trigger_value: frame.trigger_value,
+                max_volume: frame.max_volume,
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(duration: U8F24) -> FrameDescriptor {
+        FrameDescriptor {
+            flags: 0,
+            set_value: U4F4::from_num(6),
+            temp: U7F1::from_num(93),
+            duration,
+            trigger_value: U4F4::from_num(0),
+            max_volume: 0,
+        }
+    }
+
+    #[test]
+    fn to_packets_yields_header_then_frames_with_assigned_index() {
+        let mut profile = Profile::new(1, U4F4::from_num(0), U4F4::from_num(0));
+        profile.push_frame(frame(U8F24::from_num(5))).unwrap();
+        profile.push_frame(frame(U8F24::from_num(25))).unwrap();
+        profile.set_preinfuse_frames(1).unwrap();
+
+        let packets: heapless::Vec<Packet, 3> = profile.to_packets().collect();
+        let Packet::ShotHeaderWrite(header) = &packets[0] else {
+            panic!("expected a header packet first");
+        };
+        assert_eq!(header.frames, 2);
+        assert_eq!(header.preinfuse_frames, 1);
+
+        for (index, packet) in packets[1..].iter().enumerate() {
+            let Packet::ShotFrameWrite(frame) = packet else {
+                panic!("expected a frame packet");
+            };
+            assert_eq!(frame.index, index as u8);
+        }
+    }
+
+    #[test]
+    fn push_frame_rejects_a_duration_that_cannot_round_trip() {
+        // f817 only keeps whole seconds above 12.7s.
+        let mut profile = Profile::new(1, U4F4::from_num(0), U4F4::from_num(0));
+        assert_eq!(
+            profile.push_frame(frame(U8F24::from_num(25.3))),
+            Err(Error::Unknown)
+        );
+    }
+
+    #[test]
+    fn set_preinfuse_frames_rejects_more_than_pushed() {
+        let mut profile = Profile::new(1, U4F4::from_num(0), U4F4::from_num(0));
+        profile.push_frame(frame(U8F24::from_num(5))).unwrap();
+        assert_eq!(profile.set_preinfuse_frames(2), Err(Error::Unknown));
+    }
+}
@@ -103,6 +103,24 @@ fn packet(i: &str) -> IResult<&str, Frame> {
 
 impl Frame {
     pub async fn write<W: Write>(&self, mut w: W) -> Result<usize> {
+        let output = self.encode()?;
+        let data = output.as_bytes();
+        w.write_all(&data).await.map_err(|_| Error::IoError)?;
+
+        Ok(data.len())
+    }
+
+    /// Blocking counterpart of `write`, for callers on a blocking transport
+    /// (e.g. `client::SyncClient`).
+    pub fn write_blocking<W: embedded_io::Write>(&self, mut w: W) -> Result<usize> {
+        let output = self.encode()?;
+        let data = output.as_bytes();
+        w.write_all(&data).map_err(|_| Error::IoError)?;
+
+        Ok(data.len())
+    }
+
+    fn encode(&self) -> Result<String<MAX_ENCODED_LENGTH>> {
         let mut output = String::<MAX_ENCODED_LENGTH>::new();
         match self {
             Frame::FromDe1(f) => {
@@ -130,11 +148,7 @@ impl Frame {
                 output.push_str(">\n")?;
             }
         }
-
-        let data = output.as_bytes();
-        w.write_all(&data).await.map_err(|_| Error::IoError)?;
-
-        Ok(data.len())
+        Ok(output)
     }
 
     fn append_data(s: &mut String<MAX_ENCODED_LENGTH>, data: &[u8]) -> Result<()> {
@@ -0,0 +1,62 @@
+//! Abstraction over the byte stream a `De1` (real hardware or the fake
+//! emulator) talks over, so the protocol engine isn't locked to one
+//! concrete transport.
+
+/// A bidirectional, unframed byte transport: a UART, a BLE GATT
+/// characteristic pair, a TCP socket, or an in-process pipe for tests.
+pub trait Transport {
+    /// Reads into `buf`, returning the number of bytes read.
+    async fn read(&mut self, buf: &mut [u8]) -> usize;
+
+    /// Writes all of `buf`.
+    async fn write(&mut self, buf: &[u8]);
+}
+
+/// Adapts a `Transport` to the `embedded_io_async::Write` trait that
+/// `Frame::write` expects, so the wire-framing code stays transport-agnostic.
+pub(crate) struct TransportWriter<'a, T: Transport>(pub &'a mut T);
+
+impl<'a, T: Transport> embedded_io_async::ErrorType for TransportWriter<'a, T> {
+    type Error = core::convert::Infallible;
+}
+
+impl<'a, T: Transport> embedded_io_async::Write for TransportWriter<'a, T> {
+    async fn write(&mut self, buf: &[u8]) -> core::result::Result<usize, Self::Error> {
+        self.0.write(buf).await;
+        Ok(buf.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heapless::Vec;
+
+    use super::*;
+
+    struct RecordingTransport {
+        written: Vec<u8, 32>,
+    }
+
+    impl Transport for RecordingTransport {
+        async fn read(&mut self, _buf: &mut [u8]) -> usize {
+            unimplemented!()
+        }
+
+        async fn write(&mut self, buf: &[u8]) {
+            self.written.extend_from_slice(buf).unwrap();
+        }
+    }
+
+    #[futures_test::test]
+    async fn transport_writer_reports_the_full_length_written() {
+        use embedded_io_async::Write as _;
+
+        let mut transport = RecordingTransport {
+            written: Vec::new(),
+        };
+        let n = TransportWriter(&mut transport).write(b"abc").await.unwrap();
+
+        assert_eq!(n, 3);
+        assert_eq!(transport.written, b"abc");
+    }
+}